@@ -4,40 +4,36 @@ extern crate rand;
 
 use docopt::Docopt;
 use std::path;
+use std::collections::{HashMap, HashSet, VecDeque};
 use image::{
     RgbImage,
     Rgb
 };
 use rand::{
-    random,
     Rand,
     Rng
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coord {
     x: u32,
     y: u32,
 }
-type Path = Coord;
 type Wall = Coord;
 
-fn pop_random_wall(walls: &mut Vec<Wall>) -> Wall {
-    let pos: usize = rand::random::<usize>() % walls.len();
-    walls.swap_remove(pos)
-}
-
 struct Maze {
-    img: RgbImage,
-    pixel_size: u32,
-    width: u32,
-    height: u32,
+    grid: Vec<CellKind>,
     grid_width: u32,
     grid_height: u32,
+    solution: HashSet<Coord>,
+    wall_thickness: u32,
+    passage_width: u32,
     path_color: Rgb<u8>,
     wall_color: Rgb<u8>,
+    solution_color: Rgb<u8>,
 }
 
+#[derive(Clone, Copy)]
 enum Direction {
     Up,
     Down,
@@ -55,86 +51,103 @@ impl Rand for Direction {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 enum CellKind {
-    Wall,
     Path,
     Undefined
 }
 
+enum Algorithm {
+    Prim,
+    RecursiveBacktracker,
+    Wilson,
+    Eller,
+    GrowingTree(GrowBias),
+}
+
+impl Algorithm {
+    fn from_str(s: &str, bias: &str) -> Algorithm {
+        match s {
+            "" | "prim" => Algorithm::Prim,
+            "recursive-backtracker" | "backtracker" => Algorithm::RecursiveBacktracker,
+            "wilson" => Algorithm::Wilson,
+            "eller" => Algorithm::Eller,
+            "growing-tree" => Algorithm::GrowingTree(GrowBias::from_str(bias)),
+            _ => panic!("unknown algorithm: {}", s),
+        }
+    }
+}
+
+/* Cell-selection strategy for the growing-tree algorithm: always pick the
+ * most recently added cell (newest), a uniformly random one (random), or
+ * a mix of both, weighted by the given ratio towards newest. */
+enum GrowBias {
+    Newest,
+    Random,
+    Mix(f64),
+}
+
+impl GrowBias {
+    fn from_str(s: &str) -> GrowBias {
+        if s.is_empty() || s == "newest" {
+            GrowBias::Newest
+        } else if s == "random" {
+            GrowBias::Random
+        } else if s.starts_with("mix:") {
+            let ratio : f64 = s[4..].parse()
+                .ok()
+                .expect("invalid bias ratio");
+            GrowBias::Mix(ratio)
+        } else {
+            panic!("unknown bias: {}", s);
+        }
+    }
+}
+
 impl Maze {
-    fn new(width: u32, height: u32, pixel_size: u32) -> Maze {
-        let mut m = Maze {
-            width: width,
-            height: height,
-            grid_width: width / pixel_size,
-            grid_height: height / pixel_size,
-            pixel_size: pixel_size,
-            img: RgbImage::new(width, height),
+    fn new(grid_width: u32, grid_height: u32, wall_thickness: u32, passage_width: u32) -> Maze {
+        Maze {
+            grid: vec![CellKind::Undefined; (grid_width * grid_height) as usize],
+            grid_width: grid_width,
+            grid_height: grid_height,
+            solution: HashSet::new(),
+            wall_thickness: wall_thickness,
+            passage_width: passage_width,
             path_color: Rgb{data:[253, 246, 227]},
             wall_color: Rgb{data:[  7,  54,  66]},
-        };
-        /* draw right/bottom walls if needed */
-        let d = m.width - m.grid_width * m.pixel_size;
-        for i in 0..d {
-            let x = m.grid_width * m.pixel_size + i;
-            for y in 0..m.height {
-                m.img.put_pixel(x, y, m.wall_color);
-            }
+            solution_color: Rgb{data:[220,  50,  47]},
         }
-        let d = m.height - m.grid_height * m.pixel_size;
-        for i in 0..d {
-            let y = m.grid_height * m.pixel_size + i;
-            for x in 0..m.width {
-                m.img.put_pixel(x, y, m.wall_color);
-            }
-        }
-        m
+    }
+
+    fn index(&self, c: &Coord) -> usize {
+        (c.y * self.grid_width + c.x) as usize
     }
 
     fn cell_kind(&self, c: &Coord) -> CellKind {
         if c.x >= self.grid_width || c.y >= self.grid_height {
             return CellKind::Undefined;
         }
-        let p = self.img.get_pixel(c.x * self.pixel_size,
-                               c.y * self.pixel_size);
-        if *p == self.wall_color {
-            CellKind::Wall
-        } else if *p == self.path_color {
-            CellKind::Path
-        } else {
-            CellKind::Undefined
-        }
+        self.grid[self.index(c)]
     }
 
     fn draw_path(&mut self, c: &Coord) {
-        for i in 0..self.pixel_size {
-            for j in 0..self.pixel_size {
-                self.img.put_pixel(c.x * self.pixel_size + i,
-                                   c.y * self.pixel_size + j,
-                                   self.path_color);
-            }
-        }
+        let i = self.index(c);
+        self.grid[i] = CellKind::Path;
     }
 
-    fn draw_wall(&mut self, c: &Coord) {
-        for i in 0..self.pixel_size {
-            for j in 0..self.pixel_size {
-                self.img.put_pixel(c.x * self.pixel_size + i,
-                                   c.y * self.pixel_size + j,
-                                   self.wall_color);
-            }
-        }
+    fn draw_solution(&mut self, c: &Coord) {
+        self.solution.insert(*c);
     }
 
-    fn add_walls_around(&mut self, c: &Coord, walls: &mut Vec<Wall>) {
+    /* Candidate cells two steps away from `c` that aren't part of the maze
+     * yet; used by randomized_prim to grow its frontier. */
+    fn add_frontier_around(&self, c: &Coord, frontier: &mut Vec<Coord>) {
         let dirs = vec![Direction::Up, Direction::Down,
                         Direction::Left, Direction::Right];
         for d in dirs {
-            let o = self.get_coord_next(&c, d);
-            if let Some(w) = o {
-                if let CellKind::Undefined = self.cell_kind(&w) {
-                    self.draw_wall(&w as &Wall);
-                    walls.push(w as Wall);
+            if let Some((_, t)) = self.get_coord_jump(c, d) {
+                if let CellKind::Undefined = self.cell_kind(&t) {
+                    frontier.push(t);
                 }
             }
         }
@@ -177,20 +190,33 @@ impl Maze {
         }
     }
 
+    /* The cell one step away in `dir` is the wall between `c` and the cell
+     * two steps away (the target); `None` if either step falls off the
+     * grid. */
+    fn get_coord_jump(&self, c: &Coord, dir: Direction) -> Option<(Wall, Coord)> {
+        let w = match self.get_coord_next(c, dir) {
+            Some(w) => w,
+            None => return None,
+        };
+        let t = match self.get_coord_next(&w, dir) {
+            Some(t) => t,
+            None => return None,
+        };
+        Some((w, t))
+    }
+
 /* Randomized Prim's algorithm
  *
  * This algorithm is a randomized version of Prim's algorithm.
  *
  *  Start with a grid full of walls.
- *  Pick a cell, mark it as part of the maze. Add the walls of the cell to the
- *  wall list.
- *  While there are walls in the list:
- *      Pick a random wall from the list and a random direction. If the cell
- *      in that direction isn't in the maze yet:
- *          Make the wall a passage and mark the cell on the opposite side as
- *          part of the maze.
- *          Add the neighboring walls of the cell to the wall list.
- *      Remove the wall from the list.
+ *  Pick a cell, mark it as part of the maze. Add its not-yet-visited
+ *  neighbors (two steps away, via get_coord_jump) to a frontier list.
+ *  While there are cells in the frontier:
+ *      Pick a random cell from the frontier. Of its already-carved
+ *      neighbors, pick one at random and carve the wall-cell between them.
+ *      Add the picked cell's own not-yet-visited neighbors to the
+ *      frontier.
  *
  * It will usually be relatively easy to find the way to the starting cell,
  * but hard to find the way anywhere else.
@@ -207,55 +233,707 @@ impl Maze {
  * randomly chosen cell has multiple edges that connect it to the existing
  * maze, select one of these edges at random. This will tend to branch
  * slightly more than the edge-based version above.
+ *
+ * This shares the even=cell/odd=wall jump model used by every other
+ * generator here (see get_coord_jump), so --wall-thickness and
+ * --passage-width apply to it the same way they do everywhere else.
  */
 
     fn randomized_prim(&mut self) {
-        let mut walls : Vec<Wall> = Vec::new();
         let start = Coord{x:0, y:0};
         self.draw_path(&start);
-        self.add_walls_around(&start, &mut walls);
-
-        while !walls.is_empty() {
-            /* Pick a random wall from the list */
-            let w = pop_random_wall(&mut walls);
-            let o = self.get_coord_next(&w as &Coord,
-                                        rand::random::<Direction>());
-            if let Some(c) = o {
+
+        let mut frontier : Vec<Coord> = Vec::new();
+        self.add_frontier_around(&start, &mut frontier);
+
+        while !frontier.is_empty() {
+            let idx = rand::random::<usize>() % frontier.len();
+            let c = frontier.swap_remove(idx);
+            if let CellKind::Path = self.cell_kind(&c) {
+                /* may have been added to the frontier twice */
+                continue;
+            }
+
+            let mut carved_neighbors : Vec<Wall> = Vec::new();
+            let dirs = vec![Direction::Up, Direction::Down,
+                            Direction::Left, Direction::Right];
+            for d in dirs {
+                if let Some((w, t)) = self.get_coord_jump(&c, d) {
+                    if let CellKind::Path = self.cell_kind(&t) {
+                        carved_neighbors.push(w);
+                    }
+                }
+            }
+
+            let w = carved_neighbors[rand::random::<usize>() % carved_neighbors.len()];
+            self.draw_path(&w);
+            self.draw_path(&c);
+            self.add_frontier_around(&c, &mut frontier);
+        }
+    }
+/* Recursive backtracker
+ *
+ * Start at a cell, mark it as part of the maze and push it onto a stack.
+ * While the stack isn't empty, look at its top cell's neighbors in a
+ * shuffled direction order. For each direction, get_coord_jump gives back
+ * the wall-cell between the top cell and the candidate two steps away; as
+ * soon as that candidate is CellKind::Undefined, carve the wall-cell and
+ * the candidate (two draw_path calls, as randomized_prim does) and push
+ * the candidate. When the top cell has no undefined candidate left, pop
+ * it and keep backtracking.
+ *
+ * Because it always extends the most recently carved cell, this produces
+ * long, winding corridors with few branches, in contrast with the short
+ * dead ends typical of randomized_prim.
+ */
+    fn recursive_backtracker(&mut self) {
+        let start = Coord{x:0, y:0};
+        self.draw_path(&start);
+
+        let mut stack : Vec<Coord> = vec![start];
+        while let Some(c) = stack.pop() {
+            let mut dirs = vec![Direction::Up, Direction::Down,
+                                Direction::Left, Direction::Right];
+            rand::thread_rng().shuffle(&mut dirs);
+
+            let mut next = None;
+            for d in dirs {
+                if let Some((w, t)) = self.get_coord_jump(&c, d) {
+                    if let CellKind::Undefined = self.cell_kind(&t) {
+                        next = Some((w, t));
+                        break;
+                    }
+                }
+            }
+
+            if let Some((w, t)) = next {
+                self.draw_path(&w);
+                self.draw_path(&t);
+                stack.push(c);
+                stack.push(t);
+            }
+        }
+    }
+
+/* Wilson's algorithm (loop-erased random walk)
+ *
+ * Unlike randomized_prim and recursive_backtracker, which are both biased
+ * towards certain maze shapes, Wilson's algorithm picks a maze uniformly
+ * at random among all possible mazes on the grid.
+ *
+ * Mark one arbitrary cell as part of the maze. Then, until every cell is
+ * part of the maze:
+ *   Pick a random cell that isn't in the maze yet and perform a random
+ *   walk from it (through get_coord_jump, which also hands back the
+ *   wall-cell between a cell and the one it steps to), recording, for
+ *   every cell the walk visits, the direction it left through. If the
+ *   walk revisits a cell, the newly recorded direction simply overwrites
+ *   the old one. Stop as soon as the walk reaches a cell already in the
+ *   maze.
+ *
+ * Retrace the walk from its starting cell by following the recorded
+ * directions: this path is guaranteed loop-free, because only the *last*
+ * direction taken out of each cell was kept. Carve every cell and
+ * intervening wall-cell along that retraced path into the maze.
+ */
+    fn wilson(&mut self) {
+        let start = Coord{x:0, y:0};
+        self.draw_path(&start);
+
+        let cols = (self.grid_width + 1) / 2;
+        let rows = (self.grid_height + 1) / 2;
+        let mut remaining = cols * rows - 1;
+        while remaining > 0 {
+            let walk_start = loop {
+                let c = Coord{x: (rand::random::<u32>() % cols) * 2,
+                              y: (rand::random::<u32>() % rows) * 2};
                 if let CellKind::Undefined = self.cell_kind(&c) {
-                    self.add_walls_around(&c, &mut walls);
-                    self.draw_path(&c);
+                    break c;
+                }
+            };
+
+            let mut exits : HashMap<Coord, Direction> = HashMap::new();
+            let mut cur = walk_start;
+            while let CellKind::Undefined = self.cell_kind(&cur) {
+                let dir = rand::random::<Direction>();
+                let next = match self.get_coord_jump(&cur, dir) {
+                    Some((_, t)) => t,
+                    None => continue,
+                };
+                exits.insert(cur, dir);
+                cur = next;
+            }
+
+            let mut c = walk_start;
+            while let CellKind::Undefined = self.cell_kind(&c) {
+                self.draw_path(&c);
+                remaining -= 1;
+                let dir = exits[&c];
+                let (w, t) = self.get_coord_jump(&c, dir).unwrap();
+                self.draw_path(&w);
+                c = t;
+            }
+        }
+    }
+
+/* Eller's algorithm
+ *
+ * randomized_prim, recursive_backtracker and wilson all need to keep
+ * track of whichever cells are still undefined across the whole grid.
+ * Eller's algorithm instead builds the maze one logical row at a time,
+ * using only O(grid_width) bookkeeping, which matters once --geometry
+ * asks for a very tall image.
+ *
+ * Give every cell in the current row a set id (carried over from the
+ * previous row if it was connected down into this one, otherwise a fresh
+ * id). Moving left to right, randomly join horizontally-adjacent cells
+ * that are in different sets, merging their ids and carving the
+ * wall-cell between them. Then, for every distinct set remaining in the
+ * row, carve at least one random vertical passage down into the next
+ * row so the set stays connected; cells that got a vertical carve keep
+ * their set id next row, everything else starts a fresh set. On the
+ * final row, join every remaining pair of differing adjacent sets
+ * unconditionally so the whole maze ends up connected.
+ *
+ * This still carves into the shared self.grid, which costs
+ * O(grid_width * grid_height) -- that's needed here because --solve and
+ * --braid both require random access over the finished maze. When
+ * neither is requested, main() instead calls eller_stream below, which
+ * keeps the same row-at-a-time generation but paints straight into the
+ * output image and never allocates the full grid.
+ */
+    fn eller(&mut self) {
+        let cols = ((self.grid_width + 1) / 2) as usize;
+        let rows = (self.grid_height + 1) / 2;
+        if cols == 0 || rows == 0 {
+            return;
+        }
+
+        let mut next_id : u32 = 0;
+        let mut row_sets : Vec<Option<u32>> = vec![None; cols];
+
+        for ry in 0..rows {
+            let mut sets : Vec<u32> = Vec::with_capacity(cols);
+            for cx in 0..cols {
+                let id = match row_sets[cx] {
+                    Some(id) => id,
+                    None => {
+                        let id = next_id;
+                        next_id += 1;
+                        id
+                    }
+                };
+                sets.push(id);
+                self.draw_path(&Coord{x: cx as u32 * 2, y: ry * 2});
+            }
+
+            let last_row = ry == rows - 1;
+
+            for cx in 0..cols - 1 {
+                if sets[cx] == sets[cx + 1] {
+                    continue;
+                }
+                if !last_row && !rand::random::<bool>() {
+                    continue;
+                }
+                let from = sets[cx + 1];
+                let to = sets[cx];
+                for s in sets.iter_mut() {
+                    if *s == from {
+                        *s = to;
+                    }
+                }
+                self.draw_path(&Coord{x: cx as u32 * 2 + 1, y: ry * 2});
+            }
+
+            if last_row {
+                break;
+            }
+
+            let mut carved : Vec<bool> = vec![false; cols];
+            let mut cx = 0;
+            while cx < cols {
+                let id = sets[cx];
+                let mut end = cx;
+                while end < cols && sets[end] == id {
+                    end += 1;
+                }
+                let mut any = false;
+                for c in cx..end {
+                    if rand::random::<bool>() {
+                        carved[c] = true;
+                        any = true;
+                    }
+                }
+                if !any {
+                    carved[end - 1] = true;
+                }
+                cx = end;
+            }
+
+            for cx in 0..cols {
+                if carved[cx] {
+                    row_sets[cx] = Some(sets[cx]);
+                    self.draw_path(&Coord{x: cx as u32 * 2, y: ry * 2 + 1});
+                } else {
+                    row_sets[cx] = None;
+                }
+            }
+        }
+    }
+
+/* Eller's algorithm, streamed straight into the output image
+ *
+ * Same row-at-a-time generation as eller() above, but each logical row
+ * is kept in a local Vec<CellKind> of length grid_width -- discarded as
+ * soon as that row is painted -- instead of being written into
+ * self.grid. Working memory is therefore O(grid_width) for the row, plus
+ * the O(grid_width + grid_height) pixel offset tables and the output
+ * RgbImage itself, rather than O(grid_width * grid_height) for a
+ * separate CellKind grid on top of that image. The final raster still
+ * holds one block of pixels per grid position, so it grows with
+ * grid_width * grid_height; that part is inherent to producing an image
+ * and isn't bounded here.
+ *
+ * Only usable when nothing needs random access into the finished maze,
+ * i.e. neither --solve nor --braid was requested; see eller() and its
+ * caller in main().
+ */
+    fn eller_stream(&self) -> RgbImage {
+        let col_offset = self.col_offsets();
+        let row_offset = self.row_offsets();
+        let mut img = RgbImage::new(col_offset[self.grid_width as usize],
+                                     row_offset[self.grid_height as usize]);
+
+        let cols = ((self.grid_width + 1) / 2) as usize;
+        let rows = (self.grid_height + 1) / 2;
+        if cols == 0 || rows == 0 {
+            return img;
+        }
+
+        let mut next_id : u32 = 0;
+        let mut row_sets : Vec<Option<u32>> = vec![None; cols];
+
+        for ry in 0..rows {
+            let mut sets : Vec<u32> = Vec::with_capacity(cols);
+            let mut row : Vec<CellKind> = vec![CellKind::Undefined; self.grid_width as usize];
+            for cx in 0..cols {
+                let id = match row_sets[cx] {
+                    Some(id) => id,
+                    None => {
+                        let id = next_id;
+                        next_id += 1;
+                        id
+                    }
+                };
+                sets.push(id);
+                row[cx * 2] = CellKind::Path;
+            }
+
+            let last_row = ry == rows - 1;
+
+            for cx in 0..cols - 1 {
+                if sets[cx] == sets[cx + 1] {
+                    continue;
+                }
+                if !last_row && !rand::random::<bool>() {
+                    continue;
+                }
+                let from = sets[cx + 1];
+                let to = sets[cx];
+                for s in sets.iter_mut() {
+                    if *s == from {
+                        *s = to;
+                    }
+                }
+                row[cx * 2 + 1] = CellKind::Path;
+            }
+
+            self.paint_row(&mut img, ry * 2, &row, &col_offset, &row_offset);
+
+            if last_row {
+                break;
+            }
+
+            let mut carved : Vec<bool> = vec![false; cols];
+            let mut cx = 0;
+            while cx < cols {
+                let id = sets[cx];
+                let mut end = cx;
+                while end < cols && sets[end] == id {
+                    end += 1;
+                }
+                let mut any = false;
+                for c in cx..end {
+                    if rand::random::<bool>() {
+                        carved[c] = true;
+                        any = true;
+                    }
+                }
+                if !any {
+                    carved[end - 1] = true;
+                }
+                cx = end;
+            }
+
+            let mut vert_row : Vec<CellKind> = vec![CellKind::Undefined; self.grid_width as usize];
+            for cx in 0..cols {
+                if carved[cx] {
+                    row_sets[cx] = Some(sets[cx]);
+                    vert_row[cx * 2] = CellKind::Path;
+                } else {
+                    row_sets[cx] = None;
+                }
+            }
+            self.paint_row(&mut img, ry * 2 + 1, &vert_row, &col_offset, &row_offset);
+        }
+
+        img
+    }
+
+    fn paint_row(&self, img: &mut RgbImage, y: u32, row: &[CellKind],
+                 col_offset: &[u32], row_offset: &[u32]) {
+        for x in 0..self.grid_width {
+            let color = self.cell_pixel_color(row[x as usize], false);
+            self.paint_cell(img, x, y, color, col_offset, row_offset);
+        }
+    }
+
+/* Growing tree
+ *
+ * Keeps an "active" list of carved cells and repeatedly extends an
+ * undefined neighbor of one of them; only the choice of cell varies.
+ * Picking the most recently added cell behaves like
+ * recursive_backtracker's stack and gives its long corridors; picking a
+ * uniformly random one gives bushy branches like randomized_prim's
+ * (which itself grows from a frontier of uncarved cells instead);
+ * GrowBias::Mix lets callers land anywhere between the two.
+ *
+ * Seed the active list with one cell. Each step, pick a cell from it per
+ * the bias, look for an undefined neighbor through get_coord_jump; if
+ * found, carve the wall-cell and the neighbor and add the neighbor to the
+ * active list. If the picked cell has no undefined neighbor left, remove
+ * it from the list. Finish when the list is empty.
+ */
+    fn growing_tree(&mut self, bias: GrowBias) {
+        let start = Coord{x:0, y:0};
+        self.draw_path(&start);
+
+        let mut active : Vec<Coord> = vec![start];
+        while !active.is_empty() {
+            let idx = match bias {
+                GrowBias::Newest => active.len() - 1,
+                GrowBias::Random => rand::random::<usize>() % active.len(),
+                GrowBias::Mix(newest_ratio) => {
+                    if rand::random::<f64>() < newest_ratio {
+                        active.len() - 1
+                    } else {
+                        rand::random::<usize>() % active.len()
+                    }
+                }
+            };
+            let c = active[idx];
+
+            let mut dirs = vec![Direction::Up, Direction::Down,
+                                Direction::Left, Direction::Right];
+            rand::thread_rng().shuffle(&mut dirs);
+
+            let mut next = None;
+            for d in dirs {
+                if let Some((w, t)) = self.get_coord_jump(&c, d) {
+                    if let CellKind::Undefined = self.cell_kind(&t) {
+                        next = Some((w, t));
+                        break;
+                    }
+                }
+            }
+
+            match next {
+                Some((w, t)) => {
                     self.draw_path(&w);
+                    self.draw_path(&t);
+                    active.push(t);
+                }
+                None => {
+                    active.swap_remove(idx);
                 }
             }
+        }
+    }
+
+/* Solve
+ *
+ * Flood-fill the generated maze breadth-first over path-cells, treating
+ * wall-cells as impassable, recording each cell's parent. Every
+ * generator here carves whole cells rather than separate wall segments,
+ * so walking plain get_coord_next one step at a time and only entering
+ * CellKind::Path neighbors already keeps to carved corridors, whatever
+ * algorithm produced them.
+ *
+ * Because a breadth-first queue empties in non-decreasing distance
+ * order, the last cell dequeued is the farthest one from the search's
+ * start. A single BFS only gives the farthest cell *from wherever it
+ * happened to start*, which for a tree-shaped maze isn't necessarily
+ * one of the two cells that are farthest apart from *each other* (the
+ * tree's diameter). The standard double-sweep fix: BFS once from an
+ * arbitrary cell (Coord{0,0}, which every generator always carves) to
+ * find one end of the diameter, then BFS again from there -- the
+ * farthest cell from an end of the diameter is the other end. That
+ * second BFS's farthest cell is the exit, and its parent chain back to
+ * the entrance is repainted in solution_color. In a perfect maze that
+ * chain is the only path between them; after --braid has added loops it
+ * is a shortest one, and the double sweep no longer guarantees the
+ * farthest-apart pair.
+ */
+    fn bfs_farthest(&self, start: Coord) -> (Coord, HashMap<Coord, Coord>) {
+        let mut visited : HashSet<Coord> = HashSet::new();
+        let mut parent : HashMap<Coord, Coord> = HashMap::new();
+        let mut queue : VecDeque<Coord> = VecDeque::new();
+        visited.insert(start);
+        queue.push_back(start);
 
+        let mut goal = start;
+        while let Some(c) = queue.pop_front() {
+            goal = c;
+            let dirs = vec![Direction::Up, Direction::Down,
+                            Direction::Left, Direction::Right];
+            for d in dirs {
+                if let Some(n) = self.get_coord_next(&c, d) {
+                    if visited.contains(&n) {
+                        continue;
+                    }
+                    if let CellKind::Path = self.cell_kind(&n) {
+                        visited.insert(n);
+                        parent.insert(n, c);
+                        queue.push_back(n);
+                    }
+                }
+            }
         }
+        (goal, parent)
     }
-    fn save(&mut self, path: &path::Path) {
-        let _ = self.img.save(path);
+
+    fn solve(&mut self) {
+        let (entrance, _) = self.bfs_farthest(Coord{x:0, y:0});
+        let (exit, parent) = self.bfs_farthest(entrance);
+
+        let mut c = exit;
+        self.draw_solution(&c);
+        while let Some(&p) = parent.get(&c) {
+            c = p;
+            self.draw_solution(&c);
+        }
     }
-}
 
+/* Braid
+ *
+ * The generators above all build a perfect maze: a spanning tree with
+ * exactly one path between any two cells, which means every dead end is
+ * a true dead end. Braiding trades some of that uniqueness away for
+ * loops by reopening dead ends, which is what confounds a solver that
+ * just always turns whichever way it hasn't tried yet.
+ *
+ * Walk every path-cell; a dead end is one with exactly one path
+ * neighbor among the four get_coord_next directions. For each dead end,
+ * roll the braid factor, and on success look at its non-path neighbors
+ * for one that borders another path-cell elsewhere in the maze, i.e. a
+ * wall that, if carved, reconnects the dead end into a loop instead of
+ * just extending it. Prefer a candidate whose far side is itself a dead
+ * end, so a single carve removes two dead ends at once.
+ *
+ * A factor of 0.0 leaves the maze untouched; 1.0 removes every dead end
+ * it can.
+ */
+    fn path_neighbor_count(&self, c: &Coord) -> u32 {
+        let dirs = vec![Direction::Up, Direction::Down,
+                        Direction::Left, Direction::Right];
+        let mut count = 0;
+        for d in dirs {
+            if let Some(n) = self.get_coord_next(c, d) {
+                if let CellKind::Path = self.cell_kind(&n) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
 
-fn generate_image(path: &path::Path, width: u32, height: u32) {
-    let mut maze = Maze::new(width, height, 4);
+    fn braid(&mut self, factor: f64) {
+        for x in 0..self.grid_width {
+            for y in 0..self.grid_height {
+                let c = Coord{x: x, y: y};
+                match self.cell_kind(&c) {
+                    CellKind::Path => (),
+                    _ => continue,
+                }
+                if self.path_neighbor_count(&c) != 1 {
+                    continue;
+                }
+                if rand::random::<f64>() >= factor {
+                    continue;
+                }
 
-    maze.randomized_prim();
-    maze.save(path);
+                let dirs = vec![Direction::Up, Direction::Down,
+                                Direction::Left, Direction::Right];
+                let mut best : Option<Coord> = None;
+                let mut best_is_dead_end = false;
+                for d in dirs {
+                    let w = match self.get_coord_next(&c, d) {
+                        Some(w) => w,
+                        None => continue,
+                    };
+                    if let CellKind::Path = self.cell_kind(&w) {
+                        continue;
+                    }
+
+                    let wdirs = vec![Direction::Up, Direction::Down,
+                                     Direction::Left, Direction::Right];
+                    for wd in wdirs {
+                        let p = match self.get_coord_next(&w, wd) {
+                            Some(p) => p,
+                            None => continue,
+                        };
+                        if p == c {
+                            continue;
+                        }
+                        if let CellKind::Path = self.cell_kind(&p) {
+                            let is_dead_end = self.path_neighbor_count(&p) == 1;
+                            if best.is_none() || (is_dead_end && !best_is_dead_end) {
+                                best = Some(w);
+                                best_is_dead_end = is_dead_end;
+                            }
+                        }
+                    }
+                }
+
+                if let Some(w) = best {
+                    self.draw_path(&w);
+                }
+            }
+        }
+    }
+
+    fn generate(&mut self, algorithm: Algorithm) {
+        match algorithm {
+            Algorithm::Prim => self.randomized_prim(),
+            Algorithm::RecursiveBacktracker => self.recursive_backtracker(),
+            Algorithm::Wilson => self.wilson(),
+            Algorithm::Eller => self.eller(),
+            Algorithm::GrowingTree(bias) => self.growing_tree(bias),
+        }
+    }
+
+/* Render
+ *
+ * The grid above is the source of truth for maze state; rendering is a
+ * separate, final pass that paints it into pixels. Coordinates already
+ * alternate between true cells and the wall-cells between them (see
+ * get_coord_jump), so an even coordinate renders passage_width pixels
+ * wide and an odd one renders wall_thickness pixels wide, letting a
+ * thin wall separate wide corridors. Every generator, including
+ * randomized_prim, shares that same even=cell/odd=wall model (see
+ * get_coord_jump), so every generator renders through this one code path
+ * with --wall-thickness and --passage-width applying consistently.
+ *
+ * Cells still CellKind::Undefined (never reached by generation) are
+ * the uncarved walls between passages, so they're painted in
+ * wall_color; there is no separate wall kind in the grid, and nothing is
+ * left as the image's black default background.
+ *
+ * col_offsets/row_offsets and cell_pixel_color/paint_cell are split out
+ * so that eller_stream (below) can reuse the exact same pixel geometry
+ * and colour rules without going through the full CellKind grid.
+ */
+    fn col_size(&self, x: u32) -> u32 {
+        if x % 2 == 0 { self.passage_width } else { self.wall_thickness }
+    }
+
+    fn row_size(&self, y: u32) -> u32 {
+        if y % 2 == 0 { self.passage_width } else { self.wall_thickness }
+    }
+
+    fn col_offsets(&self) -> Vec<u32> {
+        let mut col_offset : Vec<u32> = vec![0; self.grid_width as usize + 1];
+        for x in 0..self.grid_width {
+            col_offset[x as usize + 1] = col_offset[x as usize] + self.col_size(x);
+        }
+        col_offset
+    }
+
+    fn row_offsets(&self) -> Vec<u32> {
+        let mut row_offset : Vec<u32> = vec![0; self.grid_height as usize + 1];
+        for y in 0..self.grid_height {
+            row_offset[y as usize + 1] = row_offset[y as usize] + self.row_size(y);
+        }
+        row_offset
+    }
+
+    fn cell_pixel_color(&self, kind: CellKind, is_solution: bool) -> Rgb<u8> {
+        match kind {
+            CellKind::Path => if is_solution { self.solution_color } else { self.path_color },
+            CellKind::Undefined => self.wall_color,
+        }
+    }
+
+    fn paint_cell(&self, img: &mut RgbImage, x: u32, y: u32, color: Rgb<u8>,
+                  col_offset: &[u32], row_offset: &[u32]) {
+        for i in 0..self.col_size(x) {
+            for j in 0..self.row_size(y) {
+                img.put_pixel(col_offset[x as usize] + i,
+                              row_offset[y as usize] + j,
+                              color);
+            }
+        }
+    }
+
+    fn render(&self) -> RgbImage {
+        let col_offset = self.col_offsets();
+        let row_offset = self.row_offsets();
+
+        let mut img = RgbImage::new(col_offset[self.grid_width as usize],
+                                     row_offset[self.grid_height as usize]);
+
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                let c = Coord{x: x, y: y};
+                let color = self.cell_pixel_color(self.cell_kind(&c), self.solution.contains(&c));
+                self.paint_cell(&mut img, x, y, color, &col_offset, &row_offset);
+            }
+        }
+        img
+    }
+
+    fn save(&self, path: &path::Path) {
+        let _ = self.render().save(path);
+    }
 }
 
+
+
 const USAGE: &'static str = "
 Maze background generator.
 
 Usage: maze [options] FILE
-       maze -g GEOM <kind> FILE
-       maze --geometry GEOM <kind> FILE
+       maze [options] <kind> FILE
        maze -h | --help
        maze -v | --version
 
 Options:
     -h, --help                            Show this message
     -v, --version                         Show the version
-    -g=<WIDTHxHEIGHT>, --geometry=<WIDTHxHEIGHT>  Geometry of the image to generate [default: 100x100]
+    -g=<WIDTHxHEIGHT>, --geometry=<WIDTHxHEIGHT>  Size of the maze's grid, in grid positions (passages and walls interleaved -- roughly WIDTH/2 x HEIGHT/2 cells) [default: 100x100]
+    --bias=<BIAS>                          Cell-selection bias for the growing-tree kind: newest, random or mix:<ratio> [default: newest]
+    --braid=<FACTOR>                       Probability of reopening each dead end into a loop, from 0.0 (perfect maze) to 1.0 (dead-end-free) [default: 0.0]
+    --solve                                Overlay the solution path between the two cells farthest apart in the maze
+    --wall-thickness=<N>                   Thickness of each wall, in pixels [default: 1]
+    --passage-width=<N>                    Width of each passage, in pixels [default: 3]
+
+Kinds:
+    prim                    Randomized Prim's algorithm (short, bushy branches) [default]
+    recursive-backtracker   Recursive backtracker (long, winding corridors)
+    wilson                  Wilson's algorithm (uniform spanning tree, unbiased)
+    eller                   Eller's algorithm (row-at-a-time, memory-bounded)
+    growing-tree            Growing tree, tunable with --bias between backtracker and prim
 ";
 
 
@@ -285,5 +963,38 @@ fn main() {
     let path = args.get_str("FILE");
     let path = path::Path::new(path);
 
-    generate_image(path, geometry.0, geometry.1);
+    let algorithm = Algorithm::from_str(args.get_str("<kind>"), args.get_str("--bias"));
+    let braid : f64 = args.get_str("--braid").parse()
+        .ok()
+        .expect("invalid braid factor");
+    let solve = args.get_bool("--solve");
+    let wall_thickness : u32 = args.get_str("--wall-thickness").parse()
+        .ok()
+        .expect("invalid wall thickness");
+    let passage_width : u32 = args.get_str("--passage-width").parse()
+        .ok()
+        .expect("invalid passage width");
+
+    /* Eller's algorithm only needs its O(grid_width) memory bound when
+     * nothing else needs random access over the finished maze. */
+    if let Algorithm::Eller = algorithm {
+        if !solve && braid <= 0.0 {
+            /* eller_stream never reads self.grid, so leave it empty */
+            let mut streamer = Maze::new(0, 0, wall_thickness, passage_width);
+            streamer.grid_width = geometry.0;
+            streamer.grid_height = geometry.1;
+            let _ = streamer.eller_stream().save(path);
+            return;
+        }
+    }
+
+    let mut maze = Maze::new(geometry.0, geometry.1, wall_thickness, passage_width);
+    maze.generate(algorithm);
+    if braid > 0.0 {
+        maze.braid(braid);
+    }
+    if solve {
+        maze.solve();
+    }
+    maze.save(path);
 }
\ No newline at end of file